@@ -1,3 +1,4 @@
+use memchr::{memchr, memchr2};
 use pyo3::prelude::*;
 
 // Whitespace lookup table
@@ -23,31 +24,44 @@ static IS_WHITESPACE_NO_NEWLINE: [bool; 256] = {
     table
 };
 
+/// Extract a byte slice from a Python `bytes` or `bytearray` object.
+fn extract_bytes<'a>(contents: &'a Bound<'_, pyo3::types::PyAny>) -> PyResult<&'a [u8]> {
+    if let Ok(bytes) = contents.downcast::<pyo3::types::PyBytes>() {
+        Ok(bytes.as_bytes())
+    } else if let Ok(bytearray) = contents.downcast::<pyo3::types::PyByteArray>() {
+        Ok(unsafe { bytearray.as_bytes() })
+    } else {
+        Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(
+            "contents must be bytes or bytearray"
+        ))
+    }
+}
+
 /// Skip whitespace and comments in OpenFOAM files
-/// 
+///
 /// This function skips over whitespace and comments (both // and /* */ style).
 /// It handles line continuations with backslash in line comments.
-/// 
+///
 /// # Arguments
 /// * `contents` - The file contents as bytes or bytearray
 /// * `pos` - Current position in the file
 /// * `newline_ok` - Whether newlines should be skipped (default: True)
-/// 
+/// * `nested` - Whether `/* */` comments may nest (default: False)
+///
 /// # Returns
 /// The new position after skipping whitespace and comments
 #[pyfunction]
-#[pyo3(signature = (contents, pos, *, newline_ok=true))]
-fn skip(contents: &Bound<'_, pyo3::types::PyAny>, mut pos: usize, newline_ok: bool) -> PyResult<usize> {
-    // Extract bytes from either bytes or bytearray
-    let contents = if let Ok(bytes) = contents.downcast::<pyo3::types::PyBytes>() {
-        bytes.as_bytes()
-    } else if let Ok(bytearray) = contents.downcast::<pyo3::types::PyByteArray>() {
-        unsafe { bytearray.as_bytes() }
-    } else {
-        return Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(
-            "contents must be bytes or bytearray"
-        ));
-    };
+#[pyo3(signature = (contents, pos, *, newline_ok=true, nested=false))]
+fn skip(
+    contents: &Bound<'_, pyo3::types::PyAny>,
+    pos: usize,
+    newline_ok: bool,
+    nested: bool,
+) -> PyResult<usize> {
+    skip_impl(extract_bytes(contents)?, pos, newline_ok, nested)
+}
+
+fn skip_impl(contents: &[u8], mut pos: usize, newline_ok: bool, nested: bool) -> PyResult<usize> {
     let is_whitespace = if newline_ok {
         &IS_WHITESPACE
     } else {
@@ -55,81 +69,693 @@ fn skip(contents: &Bound<'_, pyo3::types::PyAny>, mut pos: usize, newline_ok: bo
     };
     
     loop {
-        // Skip whitespace
+        // Skip whitespace. The whitespace set is made up of several distinct
+        // bytes (space, tab, \r, \f, \v and possibly \n), so a single-byte
+        // memchr scan wouldn't cover it; the lookup table stays scalar here.
         while pos < contents.len() && is_whitespace[contents[pos] as usize] {
             pos += 1;
         }
-        
+
         // Check if we're at the end of content
         if pos >= contents.len() {
             break;
         }
-        
+
         // Check for comments
         if pos + 1 < contents.len() {
             let next1 = contents[pos];
             let next2 = contents[pos + 1];
-            
+
             // Single-line comment //
             if next1 == b'/' && next2 == b'/' {
                 pos += 2;
                 loop {
-                    if pos >= contents.len() {
-                        break;
-                    }
-                    
-                    if contents[pos] == b'\n' {
-                        if newline_ok {
-                            pos += 1;
+                    match memchr2(b'\r', b'\n', &contents[pos..]) {
+                        None => {
+                            pos = contents.len();
+                            break;
+                        }
+                        Some(offset) => {
+                            let end = pos + offset;
+                            // A \r immediately followed by \n is a single
+                            // CRLF line ending; a lone \r terminates the
+                            // comment just like \n does.
+                            let is_crlf = contents[end] == b'\r'
+                                && end + 1 < contents.len()
+                                && contents[end + 1] == b'\n';
+                            let line_end_len = if is_crlf { 2 } else { 1 };
+                            // A backslash right before the line ending (CRLF
+                            // or otherwise) is a line continuation: the
+                            // comment keeps going past it.
+                            if end > 0 && contents[end - 1] == b'\\' {
+                                pos = end + line_end_len;
+                                continue;
+                            }
+                            pos = if newline_ok { end + line_end_len } else { end };
+                            break;
                         }
-                        break;
-                    }
-                    
-                    // Handle line continuation
-                    if contents[pos] == b'\\' && pos + 1 < contents.len() && contents[pos + 1] == b'\n' {
-                        pos += 1;
                     }
-                    
-                    pos += 1;
                 }
                 continue;
             }
-            
+
             // Multi-line comment /* */
             if next1 == b'/' && next2 == b'*' {
                 pos += 2;
-                
-                // Find the closing */
-                let mut found = false;
-                while pos + 1 < contents.len() {
-                    if contents[pos] == b'*' && contents[pos + 1] == b'/' {
-                        pos += 2;
-                        found = true;
-                        break;
+                let mut depth: u32 = 1;
+
+                // Jump directly to each candidate open/close sequence
+                // instead of advancing byte by byte.
+                loop {
+                    match memchr2(b'*', b'/', &contents[pos..]) {
+                        None => {
+                            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                                format!("Unterminated comment at position {}", pos)
+                            ));
+                        }
+                        Some(offset) => {
+                            let candidate = pos + offset;
+                            if nested
+                                && contents[candidate] == b'/'
+                                && candidate + 1 < contents.len()
+                                && contents[candidate + 1] == b'*'
+                            {
+                                depth += 1;
+                                pos = candidate + 2;
+                            } else if contents[candidate] == b'*'
+                                && candidate + 1 < contents.len()
+                                && contents[candidate + 1] == b'/'
+                            {
+                                depth -= 1;
+                                pos = candidate + 2;
+                                if depth == 0 {
+                                    break;
+                                }
+                            } else {
+                                pos = candidate + 1;
+                            }
+                        }
                     }
-                    pos += 1;
-                }
-                
-                if !found {
-                    return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
-                        format!("Unterminated comment at position {}", pos)
-                    ));
                 }
-                
+
                 continue;
             }
         }
-        
+
         // No more whitespace or comments
         break;
     }
-    
+
     Ok(pos)
 }
 
+const PUNCTUATION: &[u8] = b"{}()[];";
+
+fn is_punctuation(b: u8) -> bool {
+    PUNCTUATION.contains(&b)
+}
+
+/// Whether `contents[pos..]` looks like the start of a numeric literal
+/// (an optional sign followed by a digit, or a leading `.` followed by a digit).
+fn is_number_start(contents: &[u8], pos: usize) -> bool {
+    let b = contents[pos];
+    if b.is_ascii_digit() {
+        return true;
+    }
+    if b == b'+' || b == b'-' || b == b'.' {
+        return contents
+            .get(pos + 1)
+            .is_some_and(|next| next.is_ascii_digit());
+    }
+    false
+}
+
+/// Scan a quoted string starting at `contents[start]` (which must be `"`),
+/// returning the position just past the closing quote. Backslash escapes
+/// (including `\"`) do not terminate the string.
+fn scan_string(contents: &[u8], start: usize) -> PyResult<usize> {
+    let mut pos = start + 1;
+    loop {
+        if pos >= contents.len() {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Unterminated string at position {start}"
+            )));
+        }
+        match contents[pos] {
+            b'\\' if pos + 1 < contents.len() => pos += 2,
+            b'"' => return Ok(pos + 1),
+            _ => pos += 1,
+        }
+    }
+}
+
+/// Scan a numeric literal (int/float, optional sign and exponent) starting
+/// at `contents[start]`, returning the position just past its last byte.
+fn scan_number(contents: &[u8], start: usize) -> usize {
+    let mut pos = start;
+    if contents[pos] == b'+' || contents[pos] == b'-' {
+        pos += 1;
+    }
+    while pos < contents.len() && contents[pos].is_ascii_digit() {
+        pos += 1;
+    }
+    if pos < contents.len() && contents[pos] == b'.' {
+        pos += 1;
+        while pos < contents.len() && contents[pos].is_ascii_digit() {
+            pos += 1;
+        }
+    }
+    if pos < contents.len() && (contents[pos] == b'e' || contents[pos] == b'E') {
+        let mut exp_pos = pos + 1;
+        if exp_pos < contents.len() && (contents[exp_pos] == b'+' || contents[exp_pos] == b'-') {
+            exp_pos += 1;
+        }
+        if exp_pos < contents.len() && contents[exp_pos].is_ascii_digit() {
+            pos = exp_pos;
+            while pos < contents.len() && contents[pos].is_ascii_digit() {
+                pos += 1;
+            }
+        }
+    }
+    pos
+}
+
+/// Scan an identifier run (ASCII alphanumerics and `_`) starting at
+/// `contents[start]`, terminated by anything else (including path
+/// separators like `/` and `.`, unlike [`scan_word`]).
+fn scan_identifier(contents: &[u8], start: usize) -> usize {
+    let mut pos = start;
+    while pos < contents.len() && (contents[pos].is_ascii_alphanumeric() || contents[pos] == b'_') {
+        pos += 1;
+    }
+    pos
+}
+
+/// Scan a bareword/keyword run starting at `contents[start]`, terminated by
+/// whitespace, the start of a comment, punctuation, or a quote.
+fn scan_word(contents: &[u8], start: usize) -> usize {
+    let mut pos = start;
+    while pos < contents.len() {
+        let b = contents[pos];
+        if IS_WHITESPACE[b as usize] || is_punctuation(b) || b == b'"' {
+            break;
+        }
+        if b == b'/' && matches!(contents.get(pos + 1), Some(b'/') | Some(b'*')) {
+            break;
+        }
+        pos += 1;
+    }
+    pos
+}
+
+/// Classify and return the next OpenFOAM lexical token after `pos`.
+///
+/// # Arguments
+/// * `contents` - The file contents as bytes or bytearray
+/// * `pos` - Position to start scanning from
+/// * `nested` - Whether `/* */` comments may nest (default: False), matching
+///   the flag [`skip`] was given for the same purpose
+///
+/// # Returns
+/// `(kind, start, end)` where `kind` is one of `"punctuation"`, `"string"`,
+/// `"number"` or `"word"`, or `None` if only whitespace/comments remain.
+#[pyfunction]
+#[pyo3(signature = (contents, pos, *, nested=false))]
+fn next_token(
+    contents: &Bound<'_, pyo3::types::PyAny>,
+    pos: usize,
+    nested: bool,
+) -> PyResult<Option<(&'static str, usize, usize)>> {
+    let contents = extract_bytes(contents)?;
+    next_token_impl(contents, pos, nested)
+}
+
+fn next_token_impl(
+    contents: &[u8],
+    pos: usize,
+    nested: bool,
+) -> PyResult<Option<(&'static str, usize, usize)>> {
+    let start = skip_impl(contents, pos, true, nested)?;
+    if start >= contents.len() {
+        return Ok(None);
+    }
+
+    let b = contents[start];
+    if is_punctuation(b) {
+        return Ok(Some(("punctuation", start, start + 1)));
+    }
+    if b == b'"' {
+        let end = scan_string(contents, start)?;
+        return Ok(Some(("string", start, end)));
+    }
+    if is_number_start(contents, start) {
+        let end = scan_number(contents, start);
+        return Ok(Some(("number", start, end)));
+    }
+
+    let end = scan_word(contents, start);
+    Ok(Some(("word", start, end)))
+}
+
+/// Tokenize `contents` from `pos` to the end, returning every token as a
+/// `(kind, start, end)` span (see [`next_token`]).
+#[pyfunction]
+#[pyo3(signature = (contents, pos, *, nested=false))]
+fn tokenize(
+    contents: &Bound<'_, pyo3::types::PyAny>,
+    pos: usize,
+    nested: bool,
+) -> PyResult<Vec<(&'static str, usize, usize)>> {
+    let contents = extract_bytes(contents)?;
+    tokenize_impl(contents, pos, nested)
+}
+
+fn tokenize_impl(
+    contents: &[u8],
+    mut pos: usize,
+    nested: bool,
+) -> PyResult<Vec<(&'static str, usize, usize)>> {
+    let mut tokens = Vec::new();
+    while let Some((kind, start, end)) = next_token_impl(contents, pos, nested)? {
+        tokens.push((kind, start, end));
+        pos = end;
+    }
+    Ok(tokens)
+}
+
+/// Adapts a Python file-like object (anything exposing `.read(size)`) to
+/// `std::io::Read`, so it can be wrapped in a `BufReader` like a regular file.
+struct PyReader {
+    obj: PyObject,
+}
+
+impl std::io::Read for PyReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        Python::with_gil(|py| {
+            let chunk = self
+                .obj
+                .call_method1(py, "read", (buf.len(),))
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+            let bytes = chunk
+                .downcast_bound::<pyo3::types::PyBytes>(py)
+                .map_err(|e| std::io::Error::other(e.to_string()))?
+                .as_bytes();
+            copy_clamped(buf, bytes)
+        })
+    }
+}
+
+/// Copy `bytes` into `buf`, rejecting the read instead of panicking if a
+/// misbehaving `.read(size)` ignored `size` and returned more than fits.
+fn copy_clamped(buf: &mut [u8], bytes: &[u8]) -> std::io::Result<usize> {
+    if bytes.len() > buf.len() {
+        return Err(std::io::Error::other(format!(
+            "read() returned {} bytes, more than the requested {}",
+            bytes.len(),
+            buf.len()
+        )));
+    }
+    buf[..bytes.len()].copy_from_slice(bytes);
+    Ok(bytes.len())
+}
+
+/// Iterator over the records of a byte stream, split on a single-byte
+/// terminator (see [`byte_records`]).
+#[pyclass]
+struct ByteRecords {
+    reader: std::io::BufReader<Box<dyn std::io::Read + Send>>,
+    terminator: u8,
+}
+
+#[pymethods]
+impl ByteRecords {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(mut slf: PyRefMut<'_, Self>, py: Python<'_>) -> PyResult<Option<Py<pyo3::types::PyBytes>>> {
+        let terminator = slf.terminator;
+        let record = read_record(&mut slf.reader, terminator)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyOSError, _>(e.to_string()))?;
+        Ok(record.map(|record| pyo3::types::PyBytes::new_bound(py, &record).unbind()))
+    }
+}
+
+/// Read the next terminator-delimited record from `reader`, with the
+/// terminator stripped. Returns `None` once the reader is exhausted.
+fn read_record(reader: &mut impl std::io::BufRead, terminator: u8) -> std::io::Result<Option<Vec<u8>>> {
+    let mut record = Vec::new();
+    let n = reader.read_until(terminator, &mut record)?;
+    if n == 0 {
+        return Ok(None);
+    }
+    if record.last() == Some(&terminator) {
+        record.pop();
+    }
+    Ok(Some(record))
+}
+
+/// Stream successive records out of a file or file-like object.
+///
+/// # Arguments
+/// * `path_or_reader` - A filesystem path, or an already-open binary file-like
+///   object exposing `.read(size)`
+/// * `terminator` - The single byte separating records (default: `b'\n'`)
+///
+/// # Returns
+/// An iterator yielding each record's bytes with the terminator stripped
+#[pyfunction]
+#[pyo3(signature = (path_or_reader, terminator=None))]
+fn byte_records<'py>(
+    path_or_reader: &Bound<'py, pyo3::types::PyAny>,
+    terminator: Option<&Bound<'py, pyo3::types::PyBytes>>,
+) -> PyResult<ByteRecords> {
+    let terminator = match terminator {
+        Some(terminator) => terminator.as_bytes(),
+        None => b"\n",
+    };
+    if terminator.len() != 1 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "terminator must be a single byte",
+        ));
+    }
+    let terminator = terminator[0];
+
+    let reader: Box<dyn std::io::Read + Send> =
+        if let Ok(path) = path_or_reader.extract::<std::path::PathBuf>() {
+            Box::new(
+                std::fs::File::open(&path)
+                    .map_err(|e| PyErr::new::<pyo3::exceptions::PyOSError, _>(e.to_string()))?,
+            )
+        } else {
+            Box::new(PyReader {
+                obj: path_or_reader.clone().unbind(),
+            })
+        };
+
+    Ok(ByteRecords {
+        reader: std::io::BufReader::new(reader),
+        terminator,
+    })
+}
+
+/// Return the offset past a leading UTF-8 BOM (`EF BB BF`), or 0 if absent.
+///
+/// # Arguments
+/// * `contents` - The file contents as bytes or bytearray
+#[pyfunction]
+fn skip_bom(contents: &Bound<'_, pyo3::types::PyAny>) -> PyResult<usize> {
+    let contents = extract_bytes(contents)?;
+    Ok(skip_bom_impl(contents))
+}
+
+fn skip_bom_impl(contents: &[u8]) -> usize {
+    if contents.starts_with(b"\xEF\xBB\xBF") {
+        3
+    } else {
+        0
+    }
+}
+
+/// Detect a `#`-directive (`#include`, `#includeEtc`, `#inputMode`, `#remove`,
+/// ...) after skipping whitespace/comments, and return its name together with
+/// the span of its argument, up to the statement terminator `;`.
+///
+/// # Arguments
+/// * `contents` - The file contents as bytes or bytearray
+/// * `pos` - Position to start scanning from
+/// * `nested` - Whether `/* */` comments may nest (default: False), matching
+///   the flag [`skip`] was given for the same purpose
+///
+/// # Returns
+/// `(name, arg_start, arg_end)`, or `None` if no directive starts at `pos`
+#[pyfunction]
+#[pyo3(signature = (contents, pos, *, nested=false))]
+fn scan_directives(
+    contents: &Bound<'_, pyo3::types::PyAny>,
+    pos: usize,
+    nested: bool,
+) -> PyResult<Option<(String, usize, usize)>> {
+    let contents = extract_bytes(contents)?;
+    scan_directives_impl(contents, pos, nested)
+}
+
+fn scan_directives_impl(
+    contents: &[u8],
+    pos: usize,
+    nested: bool,
+) -> PyResult<Option<(String, usize, usize)>> {
+    let start = skip_impl(contents, pos, true, nested)?;
+    if start >= contents.len() || contents[start] != b'#' {
+        return Ok(None);
+    }
+
+    let name_start = start + 1;
+    let name_end = scan_word(contents, name_start);
+    if name_end == name_start {
+        return Ok(None);
+    }
+    let name = String::from_utf8_lossy(&contents[name_start..name_end]).into_owned();
+
+    let arg_start = skip_impl(contents, name_end, true, nested)?;
+
+    // Scan for the terminator like next_token would, so a `;` inside a
+    // quoted argument (e.g. a path or patch name) doesn't cut it short.
+    let mut cursor = arg_start;
+    let arg_end = loop {
+        match contents.get(cursor) {
+            None => break contents.len(),
+            Some(b';') => break cursor,
+            Some(b'"') => cursor = scan_string(contents, cursor)?,
+            _ => cursor += 1,
+        }
+    };
+
+    Ok(Some((name, arg_start, arg_end)))
+}
+
+/// Detect a variable reference (`$name` or `${...}`) after skipping
+/// whitespace/comments, and return its full span (including the `$` and, for
+/// the braced form, the enclosing `{}`).
+///
+/// # Arguments
+/// * `contents` - The file contents as bytes or bytearray
+/// * `pos` - Position to start scanning from
+/// * `nested` - Whether `/* */` comments may nest (default: False), matching
+///   the flag [`skip`] was given for the same purpose
+///
+/// # Returns
+/// `(start, end)`, or `None` if no variable reference starts at `pos`
+#[pyfunction]
+#[pyo3(signature = (contents, pos, *, nested=false))]
+fn scan_variable(
+    contents: &Bound<'_, pyo3::types::PyAny>,
+    pos: usize,
+    nested: bool,
+) -> PyResult<Option<(usize, usize)>> {
+    let contents = extract_bytes(contents)?;
+    scan_variable_impl(contents, pos, nested)
+}
+
+fn scan_variable_impl(contents: &[u8], pos: usize, nested: bool) -> PyResult<Option<(usize, usize)>> {
+    let start = skip_impl(contents, pos, true, nested)?;
+    if start >= contents.len() || contents[start] != b'$' {
+        return Ok(None);
+    }
+
+    if contents.get(start + 1) == Some(&b'{') {
+        let body_start = start + 2;
+        return match memchr(b'}', &contents[body_start..]) {
+            Some(offset) => Ok(Some((start, body_start + offset + 1))),
+            None => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Unterminated variable reference at position {start}"
+            ))),
+        };
+    }
+
+    let end = scan_identifier(contents, start + 1);
+    if end == start + 1 {
+        return Ok(None);
+    }
+    Ok(Some((start, end)))
+}
+
 /// A Python module implemented in Rust for performance-critical parsing operations.
 #[pymodule]
 fn foamlib_rust(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(skip, m)?)?;
+    m.add_function(wrap_pyfunction!(next_token, m)?)?;
+    m.add_function(wrap_pyfunction!(tokenize, m)?)?;
+    m.add_function(wrap_pyfunction!(byte_records, m)?)?;
+    m.add_class::<ByteRecords>()?;
+    m.add_function(wrap_pyfunction!(skip_bom, m)?)?;
+    m.add_function(wrap_pyfunction!(scan_directives, m)?)?;
+    m.add_function(wrap_pyfunction!(scan_variable, m)?)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skip_line_comment() {
+        let contents = b"// a comment\nrest";
+        assert_eq!(skip_impl(contents, 0, true, false).unwrap(), 13);
+    }
+
+    #[test]
+    fn skip_block_comment() {
+        let contents = b"/* a comment */rest";
+        assert_eq!(skip_impl(contents, 0, true, false).unwrap(), 15);
+    }
+
+    #[test]
+    fn skip_unterminated_block_comment_errors() {
+        let contents = b"/* never closed";
+        assert!(skip_impl(contents, 0, true, false).is_err());
+    }
+
+    #[test]
+    fn skip_nested_block_comment() {
+        let contents = b"/* outer /* inner */ still outer */rest";
+        // Without nested=True, the scan stops at the first `*/`.
+        assert_eq!(skip_impl(contents, 0, true, false).unwrap(), 21);
+        // With nested=True, matching `/*`/`*/` pairs are depth-counted.
+        assert_eq!(skip_impl(contents, 0, true, true).unwrap(), 35);
+    }
+
+    #[test]
+    fn skip_nested_block_comment_unterminated_errors() {
+        let contents = b"/* outer /* inner */ still open";
+        assert!(skip_impl(contents, 0, true, true).is_err());
+    }
+
+    #[test]
+    fn skip_bom_present() {
+        assert_eq!(skip_bom_impl(b"\xEF\xBB\xBFrest"), 3);
+    }
+
+    #[test]
+    fn skip_bom_absent() {
+        assert_eq!(skip_bom_impl(b"rest"), 0);
+    }
+
+    #[test]
+    fn skip_line_comment_crlf_backslash_continuation() {
+        // A comment ending in `\` right before a CRLF line ending must keep
+        // going onto the next line, same as it would for a bare `\n`.
+        let contents = b"// x\\\r\nmore\r\nrest";
+        let lf_equivalent = b"// x\\\nmore\nrest";
+        let crlf_end = skip_impl(contents, 0, true, false).unwrap();
+        let lf_end = skip_impl(lf_equivalent, 0, true, false).unwrap();
+        assert_eq!(&contents[crlf_end..], b"rest");
+        assert_eq!(&lf_equivalent[lf_end..], b"rest");
+    }
+
+    #[test]
+    fn skip_line_comment_bare_cr_terminates() {
+        let contents = b"// comment\rrest";
+        assert_eq!(skip_impl(contents, 0, true, false).unwrap(), 11);
+    }
+
+    #[test]
+    fn next_token_classifies_each_kind() {
+        let contents = br#"{ "a string" 42 -3.5e2 keyword }"#;
+        let mut pos = 0;
+        let mut kinds = Vec::new();
+        while let Some((kind, start, end)) = next_token_impl(contents, pos, false).unwrap() {
+            kinds.push(kind);
+            pos = end;
+            assert!(start < end);
+        }
+        assert_eq!(
+            kinds,
+            vec!["punctuation", "string", "number", "number", "word", "punctuation"]
+        );
+    }
+
+    #[test]
+    fn next_token_respects_nested_flag() {
+        let contents = b"/* outer /* inner */ still outer */word";
+        // Without nested, the scan treats the first `*/` as the close and
+        // starts tokenizing the leftover comment text ("still") as a word.
+        let (kind, start, end) = next_token_impl(contents, 0, false).unwrap().unwrap();
+        assert_eq!(kind, "word");
+        assert_eq!(&contents[start..end], b"still");
+        // With nested, the whole nested comment is skipped and the real
+        // token after it ("word") is returned instead.
+        let (kind, start, end) = next_token_impl(contents, 0, true).unwrap().unwrap();
+        assert_eq!(kind, "word");
+        assert_eq!(&contents[start..end], b"word");
+    }
+
+    #[test]
+    fn tokenize_collects_all_tokens() {
+        let contents = b"a b c;";
+        let tokens = tokenize_impl(contents, 0, false).unwrap();
+        assert_eq!(tokens.len(), 4);
+    }
+
+    #[test]
+    fn read_record_splits_on_custom_terminator() {
+        let mut reader = std::io::Cursor::new(b"one;two;three".to_vec());
+        assert_eq!(read_record(&mut reader, b';').unwrap().unwrap(), b"one");
+        assert_eq!(read_record(&mut reader, b';').unwrap().unwrap(), b"two");
+        assert_eq!(read_record(&mut reader, b';').unwrap().unwrap(), b"three");
+        assert!(read_record(&mut reader, b';').unwrap().is_none());
+    }
+
+    #[test]
+    fn read_record_on_empty_reader_returns_none() {
+        let mut reader = std::io::Cursor::new(Vec::new());
+        assert!(read_record(&mut reader, b'\n').unwrap().is_none());
+    }
+
+    #[test]
+    fn copy_clamped_copies_when_it_fits() {
+        let mut buf = [0u8; 8];
+        let n = copy_clamped(&mut buf, b"hello").unwrap();
+        assert_eq!(n, 5);
+        assert_eq!(&buf[..5], b"hello");
+    }
+
+    #[test]
+    fn copy_clamped_rejects_oversized_read_instead_of_panicking() {
+        // A misbehaving `.read(size)` that ignores `size` must surface a
+        // clean error, not panic with an out-of-bounds slice index.
+        let mut buf = [0u8; 2];
+        assert!(copy_clamped(&mut buf, b"too long").is_err());
+    }
+
+    #[test]
+    fn scan_directives_quoted_semicolon_does_not_truncate_arg() {
+        let contents = br#"#include "foo;bar.H" ;"#;
+        let (name, start, end) = scan_directives_impl(contents, 0, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(name, "include");
+        assert_eq!(&contents[start..end], br#""foo;bar.H" "#);
+    }
+
+    #[test]
+    fn scan_directives_without_hash_returns_none() {
+        assert!(scan_directives_impl(b"include foo;", 0, false)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn scan_variable_bare_name_stops_at_path_separator() {
+        let contents = b"$FOAM_CASE/constant";
+        let (start, end) = scan_variable_impl(contents, 0, false).unwrap().unwrap();
+        assert_eq!(&contents[start..end], b"$FOAM_CASE");
+    }
+
+    #[test]
+    fn scan_variable_braced_form_spans_to_closing_brace() {
+        let contents = b"${FOAM_CASE}/constant";
+        let (start, end) = scan_variable_impl(contents, 0, false).unwrap().unwrap();
+        assert_eq!(&contents[start..end], b"${FOAM_CASE}");
+    }
+}